@@ -6,6 +6,7 @@ use std::str;
 use std::{fs, io};
 use std::{path::Path};
 use std::time::{SystemTime, UNIX_EPOCH};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use rustop::opts;
 use filesize::PathExt;
 use memmap::Mmap;
@@ -18,7 +19,16 @@ use sysinfo::CpuExt;
 use sysinfo::PidExt;
 use sysinfo::{ProcessExt, System, SystemExt, DiskExt};
 use arrayvec::ArrayVec;
-use walkdir::WalkDir;
+use walkdir::{DirEntry, WalkDir};
+use rayon::prelude::*;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use goblin::pe::PE;
+// Pinned to the interprocess 1.x API ("interprocess = \"1\"" in Cargo.toml): LocalSocketListener
+// and LocalSocketStream are top-level re-exports of local_socket there. The 2.x line renamed and
+// restructured this module, so pulling in an unpinned/newer interprocess will not resolve these.
+use interprocess::local_socket::{LocalSocketListener, LocalSocketStream};
+use serde::Deserialize;
+use std::io::BufRead;
 use csv::Error as csvError;
 use csv::ReaderBuilder;
 use human_bytes::human_bytes;
@@ -27,6 +37,9 @@ use md5::*;
 use sha1::*;
 use memmap::MmapOptions;
 use yara::*;
+use serde_json::json;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
 
 // Specific TODOs
 // - skipping non-local file systems like network mounts or cloudfs drives
@@ -60,24 +73,175 @@ const FILE_TYPES: &'static [&'static str] = &[
 struct GenMatch {
     message: String,
     score: u16,
+    offsets: Vec<u64>,
+    strings: Vec<String>,
 }
 
 struct YaraMatch {
     rulename: String,
     score: u16,
+    offsets: Vec<u64>,
+    strings: Vec<String>,
 }
 
 struct ScanConfig {
     max_file_size: usize,
     show_access_errors: bool,
     scan_all_types: bool,
+    streaming_threshold: usize,
+    streaming_buffer_size: usize,
+    path_filter: PathFilter,
+    fuzzy_threshold: u8,
+    scan_network_drives: bool,
+}
+
+// Encode a byte buffer as a JSON string value: plain UTF-8 when valid, otherwise base64 with a
+// "base64:" prefix. `Finding::to_json` runs a finding's target and message through it; findings
+// carry matched string identifiers and offsets only, not the matched bytes themselves.
+fn json_safe_string(bytes: &[u8]) -> String {
+    match str::from_utf8(bytes) {
+        Ok(s) => s.to_string(),
+        Err(_) => format!("base64:{}", base64::encode(bytes)),
+    }
+}
+
+// Output format for the findings file written at the end of the scan
+#[derive(Debug, Clone, Copy)]
+enum OutputFormat {
+    Text,
+    Json,
+    Ndjson,
+}
+
+impl OutputFormat {
+    fn parse(value: &str) -> OutputFormat {
+        match value.to_ascii_lowercase().as_str() {
+            "json" => OutputFormat::Json,
+            "ndjson" => OutputFormat::Ndjson,
+            _ => OutputFormat::Text,
+        }
+    }
+}
+
+// Bucket a match's score into a severity label for downstream triage/filtering
+fn severity_for_score(score: u16) -> &'static str {
+    match score {
+        100..=u16::MAX => "critical",
+        75..=99 => "high",
+        50..=74 => "medium",
+        _ => "low",
+    }
+}
+
+// A single structured finding: one YARA match or hash-IOC hit, enriched with the context
+// needed for SIEM ingestion (host, time, severity) independent of its log representation.
+#[derive(Clone)]
+struct Finding {
+    timestamp: String,
+    hostname: String,
+    target: String, // file path, or "PID:<pid> PROCESS:<name>" for process hits
+    message: String,
+    score: u16,
+    severity: &'static str,
+    total_score: u16, // aggregate score of every match reported alongside this one
+    offsets: Vec<u64>, // matched string offsets, if this finding came from a YARA string match
+    strings: Vec<String>, // matched string identifiers (e.g. "$a"), if any
+}
+
+impl Finding {
+    fn to_json(&self) -> serde_json::Value {
+        json!({
+            "timestamp": self.timestamp,
+            "hostname": self.hostname,
+            "target": json_safe_string(self.target.as_bytes()),
+            "message": json_safe_string(self.message.as_bytes()),
+            "score": self.score,
+            "severity": self.severity,
+            "total_score": self.total_score,
+            "offsets": self.offsets,
+            "strings": self.strings,
+        })
+    }
+}
+
+// Collects every finding from the scan and, depending on --format, reports it as a human log
+// line (always) and/or a structured record written to --output once the scan finishes: a
+// single JSON array for "json", one record per line for "ndjson". "text" (the default) only
+// logs, same as before this subsystem existed.
+struct FindingsCollector {
+    format: OutputFormat,
+    output_path: Option<String>,
+    hostname: String,
+    findings: Mutex<Vec<Finding>>,
+}
+
+impl FindingsCollector {
+    fn new(format: OutputFormat, output_path: Option<String>, hostname: String) -> FindingsCollector {
+        FindingsCollector { format, output_path, hostname, findings: Mutex::new(Vec::new()) }
+    }
+
+    fn report_file_match(&self, path: &Path, sample_info: &SampleInfo, matches: &[GenMatch], total_score: u16) {
+        log::warn!("File match found FILE: {} {:?} SCORE: {} REASONS: {:?}", path.display(), sample_info, total_score, matches);
+        self.collect(path.display().to_string(), matches, total_score);
+    }
+
+    fn report_process_match(&self, pid: u32, process_name: &str, matches: &[GenMatch], total_score: u16) {
+        log::warn!("Process with matches found PID: {} PROCESS: {} REASONS: {:?}", pid, process_name, matches);
+        self.collect(format!("PID:{} PROCESS:{}", pid, process_name), matches, total_score);
+    }
+
+    fn collect(&self, target: String, matches: &[GenMatch], total_score: u16) {
+        if let OutputFormat::Text = self.format { return; }
+        let timestamp = Utc::now().to_rfc3339();
+        let mut findings = self.findings.lock().unwrap();
+        for sample_match in matches {
+            findings.push(Finding {
+                timestamp: timestamp.clone(),
+                hostname: self.hostname.clone(),
+                target: target.clone(),
+                message: sample_match.message.clone(),
+                score: sample_match.score,
+                severity: severity_for_score(sample_match.score),
+                total_score,
+                offsets: sample_match.offsets.clone(),
+                strings: sample_match.strings.clone(),
+            });
+        }
+    }
+
+    // Snapshot the findings collected so far, e.g. to hand back as a daemon request's response
+    // without tearing down the collector.
+    fn findings_snapshot(&self) -> Vec<Finding> {
+        self.findings.lock().unwrap().clone()
+    }
+
+    // Write the collected findings to --output. Called once after the scan finishes.
+    fn finalize(&self) {
+        let output_path = match &self.output_path {
+            Some(output_path) => output_path,
+            None => return,
+        };
+        let findings = self.findings.lock().unwrap();
+        let result = match self.format {
+            OutputFormat::Text => return,
+            OutputFormat::Json => {
+                let records: Vec<serde_json::Value> = findings.iter().map(Finding::to_json).collect();
+                fs::write(output_path, serde_json::to_string_pretty(&records).unwrap_or_default())
+            },
+            OutputFormat::Ndjson => {
+                let lines: Vec<String> = findings.iter().map(|finding| finding.to_json().to_string()).collect();
+                fs::write(output_path, lines.join("\n") + "\n")
+            },
+        };
+        if let Err(e) = result {
+            log::error!("Cannot write findings output PATH: {} ERROR: {:?}", output_path, e);
+        }
+    }
 }
 
 #[derive(Debug)]
 struct SampleInfo {
-    MD5: String,
-    SHA1: String,
-    SHA256: String,
+    hashes: BTreeMap<String, String>,
     atime: String,
     mtime: String,
     ctime: String,
@@ -100,18 +264,171 @@ struct HashIOC {
     score: u16,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 enum HashType {
     Md5,
     Sha1,
     Sha256,
+    Blake3,
+    Xxh3,
+    Crc32,
+    // PE import hash: not a streaming digest (needs the whole import table), so `make_hasher`
+    // returns None for it and it's computed separately by `compute_imphash`.
+    Imphash,
     Unknown
 }
 
-// TODO: under construction - the data structure to hold the IOCs is still limited to 100.000 elements. 
-//       I have to find a data structure that allows to store an unknown number of entries.
-// Initialize the IOCs
-fn initialize_hash_iocs() -> Vec<HashIOC> {
+impl HashType {
+    // Label used both as a CSV "type" column value and as the key for computed hashes
+    fn label(&self) -> &'static str {
+        match self {
+            HashType::Md5 => "MD5",
+            HashType::Sha1 => "SHA1",
+            HashType::Sha256 => "SHA256",
+            HashType::Blake3 => "BLAKE3",
+            HashType::Xxh3 => "XXH3",
+            HashType::Crc32 => "CRC32",
+            HashType::Imphash => "IMPHASH",
+            HashType::Unknown => "UNKNOWN",
+        }
+    }
+}
+
+// A fuzzy (ssdeep/CTPH) hash IOC. Unlike the other algorithms this can't be indexed by exact
+// value - a file matches if its ssdeep hash is *similar enough* to a known one - so these are
+// kept in their own list and scored against `scan_config.fuzzy_threshold` instead.
+#[derive(Debug)]
+struct FuzzyHashIOC {
+    ssdeep_hash: String,
+    description: String,
+    score: u16,
+}
+
+// A single incremental hash algorithm, fed the sample bytes once and finalized at the end.
+// Implemented per algorithm so `scan_path` can instantiate only the ones IOCs actually need.
+trait FileHasher {
+    fn update(&mut self, bytes: &[u8]);
+    fn finalize(self: Box<Self>) -> String;
+}
+
+struct Sha256Hasher(Sha256);
+impl FileHasher for Sha256Hasher {
+    fn update(&mut self, bytes: &[u8]) { sha2::Digest::update(&mut self.0, bytes); }
+    fn finalize(self: Box<Self>) -> String { hex::encode(self.0.finalize()) }
+}
+
+struct Sha1Hasher(Sha1);
+impl FileHasher for Sha1Hasher {
+    fn update(&mut self, bytes: &[u8]) { sha1::Digest::update(&mut self.0, bytes); }
+    fn finalize(self: Box<Self>) -> String { hex::encode(self.0.finalize()) }
+}
+
+struct Md5Hasher(md5::Context);
+impl FileHasher for Md5Hasher {
+    fn update(&mut self, bytes: &[u8]) { self.0.consume(bytes); }
+    fn finalize(self: Box<Self>) -> String { format!("{:x}", self.0.finalize()) }
+}
+
+struct Blake3Hasher(blake3::Hasher);
+impl FileHasher for Blake3Hasher {
+    fn update(&mut self, bytes: &[u8]) { self.0.update(bytes); }
+    fn finalize(self: Box<Self>) -> String { self.0.finalize().to_hex().to_string() }
+}
+
+struct Xxh3Hasher(xxhash_rust::xxh3::Xxh3);
+impl FileHasher for Xxh3Hasher {
+    fn update(&mut self, bytes: &[u8]) { self.0.update(bytes); }
+    fn finalize(self: Box<Self>) -> String { format!("{:016x}", self.0.digest()) }
+}
+
+struct Crc32Hasher(crc32fast::Hasher);
+impl FileHasher for Crc32Hasher {
+    fn update(&mut self, bytes: &[u8]) { self.0.update(bytes); }
+    fn finalize(self: Box<Self>) -> String { format!("{:08x}", self.0.finalize()) }
+}
+
+// Build the hasher for a given hash type, or None for types we can't compute (e.g. Unknown)
+fn make_hasher(hash_type: &HashType) -> Option<Box<dyn FileHasher>> {
+    match hash_type {
+        HashType::Md5 => Some(Box::new(Md5Hasher(md5::Context::new()))),
+        HashType::Sha1 => Some(Box::new(Sha1Hasher(Sha1::new()))),
+        HashType::Sha256 => Some(Box::new(Sha256Hasher(Sha256::new()))),
+        HashType::Blake3 => Some(Box::new(Blake3Hasher(blake3::Hasher::new()))),
+        HashType::Xxh3 => Some(Box::new(Xxh3Hasher(xxhash_rust::xxh3::Xxh3::new()))),
+        HashType::Crc32 => Some(Box::new(Crc32Hasher(crc32fast::Hasher::new()))),
+        HashType::Imphash | HashType::Unknown => None,
+    }
+}
+
+// Collect the distinct hash types that actually appear in the loaded IOC set, so scanning
+// doesn't pay for algorithms nobody's IOC file references.
+fn required_hash_types(hash_iocs: &HashMap<HashType, HashMap<String, HashIOC>>) -> HashSet<HashType> {
+    hash_iocs.keys().copied().collect()
+}
+
+// Compute all the hashes a file needs in a single pass: mmap small files (fastest), but stream
+// files above the configured threshold in fixed-size chunks so huge files (disk images, memory
+// dumps) never have to be mapped into address space whole. Zero-length files are not mapped and
+// hash as empty input; unreadable files return an error instead of panicking.
+fn compute_file_hashes(file_handle: &File, file_size: u64, needed_hash_types: &HashSet<HashType>, scan_config: &ScanConfig) -> io::Result<HashMap<HashType, String>> {
+    let mut hashers: Vec<(HashType, Box<dyn FileHasher>)> = needed_hash_types.iter()
+        .filter_map(|hash_type| make_hasher(hash_type).map(|hasher| (*hash_type, hasher)))
+        .collect();
+    if file_size == 0 {
+        // Nothing to feed the hashers; they finalize over empty input
+    } else if file_size > scan_config.streaming_threshold as u64 {
+        let mut reader = io::BufReader::new(file_handle);
+        let mut buffer = vec![0u8; scan_config.streaming_buffer_size];
+        loop {
+            let bytes_read = reader.read(&mut buffer)?;
+            if bytes_read == 0 { break; }
+            for (_, hasher) in hashers.iter_mut() {
+                hasher.update(&buffer[..bytes_read]);
+            }
+        }
+    } else {
+        let mmap = unsafe { MmapOptions::new().map(file_handle)? };
+        for (_, hasher) in hashers.iter_mut() {
+            hasher.update(&mmap);
+        }
+    }
+    Ok(hashers.into_iter().map(|(hash_type, hasher)| (hash_type, hasher.finalize())).collect())
+}
+
+// Compute a PE imphash: MD5 of the lowercase "dllname.functionname" list (dll name with a ".dll"
+// or ".DLL" suffix trimmed), joined by commas, in import order. Imports by ordinal use the name
+// goblin gives them, lowercased, rather than pefile's "ordN" form. Only meaningful for PE files -
+// anything that doesn't parse as one (including every non-PE file) yields None.
+fn compute_imphash(file_handle: &File) -> Option<String> {
+    let mmap = unsafe { MmapOptions::new().map(file_handle).ok()? };
+    let pe = PE::parse(&mmap).ok()?;
+    if pe.imports.is_empty() {
+        return None;
+    }
+    let entries: Vec<String> = pe.imports.iter().map(|import| {
+        let dll = import.dll.trim_end_matches(".dll").trim_end_matches(".DLL").to_ascii_lowercase();
+        format!("{}.{}", dll, import.name.to_ascii_lowercase())
+    }).collect();
+    let mut hasher = md5::Context::new();
+    hasher.consume(entries.join(",").as_bytes());
+    Some(format!("{:x}", hasher.finalize()))
+}
+
+// Fuzzy-hash (ssdeep/CTPH) a file for similarity comparison against the fuzzy IOC list. Needs
+// the whole file in memory like imphash, so it reuses the mmap size policy: files above the
+// streaming threshold are skipped rather than mapped in whole.
+fn compute_ssdeep(file_handle: &File, file_size: u64, scan_config: &ScanConfig) -> Option<String> {
+    if file_size == 0 || file_size > scan_config.streaming_threshold as u64 {
+        return None;
+    }
+    let mmap = unsafe { MmapOptions::new().map(file_handle).ok()? };
+    ssdeep::hash(&mmap[..]).ok()
+}
+
+// Initialize the IOCs, indexed by algorithm and then by normalized lowercase hash value so
+// matching a file's hash against however many IOCs are loaded is an O(1) lookup per algorithm
+// instead of a linear scan, and there's no longer a hard cap on how many IOCs can be loaded.
+fn initialize_hash_iocs() -> (HashMap<HashType, HashMap<String, HashIOC>>, Vec<FuzzyHashIOC>) {
     // Compose the location of the hash IOC file
     let hash_ioc_file = format!("{}/iocs/hash-iocs.txt", SIGNATURE_SOURCE);
     // Read the hash IOC file
@@ -121,8 +438,10 @@ fn initialize_hash_iocs() -> Vec<HashIOC> {
         .delimiter(b';')
         .flexible(true)
         .from_reader(hash_iocs_string.as_bytes());
-    // Vector that holds the hashes
-    let mut hash_iocs:Vec<HashIOC> = Vec::new();
+    // Index of hashes, keyed by algorithm and then by the lowercase hash value
+    let mut hash_iocs: HashMap<HashType, HashMap<String, HashIOC>> = HashMap::new();
+    // Fuzzy (ssdeep) IOCs can't be indexed exactly, so they live in their own list
+    let mut fuzzy_iocs: Vec<FuzzyHashIOC> = Vec::new();
     // Read the lines from the CSV file
     for result in reader.records() {
         let record_result = result;
@@ -130,36 +449,70 @@ fn initialize_hash_iocs() -> Vec<HashIOC> {
             Ok(r) => r,
             Err(e) => { log::debug!("Cannot read line in hash IOCs file (which can be okay) ERROR: {:?}", e); continue;}
         };
-        // If more than two elements have been found
+        // If more than one element has been found
         if record.len() > 1 {
             // if it's not a comment line
             if !record[0].starts_with("#") {
-                // determining hash type
-                let hash_type: HashType = get_hash_type(&record[0]);
-                log::trace!("Read hash IOC from from HASH: {} DESC: {} TYPE: {:?}", &record[0], &record[1], hash_type);
-                hash_iocs.push(
-                    HashIOC { 
+                // Format is either `hash;description` or `hash;type;description`, the latter
+                // letting IOC files disambiguate e.g. BLAKE3 from SHA256 (both 64 hex chars)
+                let (explicit_type, description) = if record.len() > 2 {
+                    (Some(&record[1]), &record[2])
+                } else {
+                    (None, &record[1])
+                };
+                // ssdeep hashes are matched by similarity, not exact value - route them to the
+                // fuzzy IOC list instead of the per-algorithm exact-match index
+                if explicit_type.map(|t| t.eq_ignore_ascii_case("ssdeep")).unwrap_or(false) {
+                    log::trace!("Read fuzzy hash IOC HASH: {} DESC: {}", &record[0], description);
+                    fuzzy_iocs.push(FuzzyHashIOC {
+                        ssdeep_hash: record[0].to_string(),
+                        description: description.to_string(),
+                        score: 100,  // TODO
+                    });
+                    continue;
+                }
+                let hash_type: HashType = get_hash_type(&record[0], explicit_type);
+                log::trace!("Read hash IOC from from HASH: {} DESC: {} TYPE: {:?}", &record[0], description, hash_type);
+                let hash_value = record[0].to_ascii_lowercase();
+                hash_iocs.entry(hash_type).or_default().insert(
+                    hash_value.clone(),
+                    HashIOC {
                         hash_type: hash_type,
-                        hash_value: record[0].to_ascii_lowercase(), 
-                        description: record[1].to_string(), 
-                        score: 100,  // TODO 
+                        hash_value: hash_value,
+                        description: description.to_string(),
+                        score: 100,  // TODO
                     });
             }
         }
     }
-    return hash_iocs;
+    return (hash_iocs, fuzzy_iocs);
 }
 
-// Get the hash type
-fn get_hash_type(hash_value: &str) -> HashType {
+// Get the hash type, preferring an explicit type column (e.g. from the IOC CSV) over the
+// length-based guess, since SHA256 and BLAKE3 digests are both 64 hex characters long.
+fn get_hash_type(hash_value: &str, explicit_type: Option<&str>) -> HashType {
+    if let Some(explicit_type) = explicit_type {
+        return match explicit_type.to_ascii_lowercase().as_str() {
+            "md5" => HashType::Md5,
+            "sha1" => HashType::Sha1,
+            "sha256" => HashType::Sha256,
+            "blake3" => HashType::Blake3,
+            "xxh3" => HashType::Xxh3,
+            "crc32" => HashType::Crc32,
+            "imphash" => HashType::Imphash,
+            _ => HashType::Unknown,
+        };
+    }
     let hash_value_length = hash_value.len();
     match hash_value_length {
+        8 => HashType::Crc32,
+        16 => HashType::Xxh3,
         32 => HashType::Md5,
         40 => HashType::Sha1,
-        64 => HashType::Sha256,
+        64 => HashType::Sha256, // BLAKE3 collides on length; use the explicit type column for that
         _ => HashType::Unknown,
     }
-} 
+}
 
 // Initialize the rule files
 fn initialize_rules() -> Rules {
@@ -228,209 +581,381 @@ fn compile_yara_rules(rules_string: &str) -> Result<Rules, Error> {
     return Ok(compiled_rules);
 }
 
-// Scan process memory of all processes
-fn scan_processes(compiled_rules: &Rules, scan_config: &ScanConfig) ->() {
-    // Refresh the process information
+// File systems that back network shares or cloud-sync drives; scanning these is slow and
+// usually unwanted
+const NETWORK_FS_TYPES: &'static [&'static str] = &[
+    "nfs", "nfs4", "cifs", "smb", "smbfs", "smb3", "fuse.sshfs", "sshfs", "9p", "afs", "afpfs", "webdav",
+];
+
+// The mount points `scan_path` is allowed to descend into, resolved by `classify_mounts`
+// before each file system scan (once per run, or once per daemon scan-path request)
+struct MountInfo {
+    // all known mount points, longest first, for prefix matching
+    mounts_by_length: Vec<String>,
+    scannable: HashSet<String>,
+}
+
+impl MountInfo {
+    // Resolve the path's mount point and check whether it was classified as scannable.
+    // Paths under no known mount point are scanned (e.g. no disk info available).
+    fn is_scannable(&self, path: &Path) -> bool {
+        match self.mounts_by_length.iter().find(|mount| path.starts_with(mount.as_str())) {
+            Some(mount) => self.scannable.contains(mount),
+            None => true,
+        }
+    }
+}
+
+// Classify each mounted disk as scannable or not, based on its file system type and the
+// removable flag, so network/remote and removable mounts can be skipped by default.
+fn classify_mounts(scan_network_drives: bool) -> MountInfo {
     let mut sys = System::new_all();
-    sys.refresh_all();
-    for (pid, process) in sys.processes() {
-        // Debug output : show every file that gets scanned
-        log::debug!("Scanning process PID: {} NAME: {}", pid, process.name());
-        // ------------------------------------------------------------
-        // Matches (all types)
-        let mut proc_matches = ArrayVec::<GenMatch, 100>::new();
-        // ------------------------------------------------------------
-        // YARA scanning
-        let yara_matches = 
-            compiled_rules.scan_process(pid.as_u32(), 30);
-        log::debug!("Scan result: {:?}", yara_matches);
-        match &yara_matches {
-            Ok(_) => {},
-            Err(e) => {
-                if scan_config.show_access_errors { log::error!("Error while scanning process memory PROCESS: {} ERROR: {:?}", process.name(), e); }
-                else { log::debug!("Error while scanning process memory PROCESS: {} ERROR: {:?}", process.name(), e); }
+    sys.refresh_disks_list();
+    let mut mounts_by_length: Vec<String> = Vec::new();
+    let mut scannable: HashSet<String> = HashSet::new();
+    for disk in sys.disks() {
+        let mount_point = disk.mount_point().to_string_lossy().to_string();
+        let fs_type = str::from_utf8(disk.file_system()).unwrap_or("").to_ascii_lowercase();
+        mounts_by_length.push(mount_point.clone());
+        let is_network_fs = NETWORK_FS_TYPES.iter().any(|known| fs_type.contains(known));
+        if (is_network_fs || disk.is_removable()) && !scan_network_drives {
+            log::info!("Skipping non-local mount point MOUNT: {} FS_TYPE: {} REMOVABLE: {}",
+                mount_point, fs_type, disk.is_removable());
+            continue;
+        }
+        scannable.insert(mount_point);
+    }
+    // Longest prefix first so nested mount points (e.g. a network share under a local root) win
+    mounts_by_length.sort_by_key(|mount| std::cmp::Reverse(mount.len()));
+    MountInfo { mounts_by_length, scannable }
+}
+
+// Include/exclude glob filtering plus an optional extension allowlist for FileScan, resolved
+// once at startup from --include/--exclude/--ignore-file/--extensions and consulted by
+// `scan_entry` before a candidate file is even opened.
+struct PathFilter {
+    include: Option<GlobSet>,
+    exclude: GlobSet,
+    allowed_extensions: Option<HashSet<String>>,
+}
+
+impl PathFilter {
+    // Excludes win over includes, mirroring .gitignore semantics. No include patterns means
+    // "don't narrow", i.e. everything not excluded (and matching the extension cap) is scanned.
+    fn is_allowed(&self, path: &Path) -> bool {
+        if self.exclude.is_match(path) {
+            return false;
+        }
+        if let Some(include) = &self.include {
+            if !include.is_match(path) {
+                return false;
             }
         }
-        // TODO: better scan error handling (debug messages)
-        for ymatch in yara_matches.unwrap_or_default().iter() {
-            if !proc_matches.is_full() {
-                let match_message: String = format!("YARA match with rule {:?}", ymatch.identifier);
-                //println!("{}", match_message);
-                proc_matches.insert(
-                    proc_matches.len(), 
-                    // TODO: get score from meta data in a safe way
-                    GenMatch{message: match_message, score: 75}
-                );
+        if let Some(allowed_extensions) = &self.allowed_extensions {
+            let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("").to_ascii_lowercase();
+            if !allowed_extensions.contains(&extension) {
+                return false;
             }
         }
+        true
+    }
+}
 
-        // Show matches on process
-        if proc_matches.len() > 0 {
-            log::warn!("Process with matches found PID: {} PROCESS: {} REASONS: {:?}", 
-            pid, process.name(), proc_matches);
+// Build a GlobSet from a list of patterns, skipping (and warning about) any pattern that fails
+// to parse instead of aborting the whole scan over one bad glob.
+fn build_glob_set(patterns: &[String]) -> GlobSet {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        match Glob::new(pattern) {
+            Ok(glob) => { builder.add(glob); },
+            Err(e) => log::warn!("Ignoring invalid glob pattern PATTERN: {} ERROR: {:?}", pattern, e),
         }
     }
+    builder.build().unwrap_or_else(|e| {
+        log::warn!("Cannot build glob set, falling back to an empty one ERROR: {:?}", e);
+        GlobSetBuilder::new().build().unwrap()
+    })
+}
+
+// Compose the path filter from the CLI-supplied include/exclude glob lists, an optional
+// ignore-file of additional exclude patterns (one pattern per line, '#' starts a comment, blank
+// lines skipped - the same convention as .gitignore), and an optional extension allowlist.
+fn build_path_filter(include_patterns: &[String], exclude_patterns: &[String], ignore_file: Option<&str>, allowed_extensions: Option<&str>) -> PathFilter {
+    let mut exclude_patterns = exclude_patterns.to_vec();
+    if let Some(ignore_file) = ignore_file {
+        match fs::read_to_string(ignore_file) {
+            Ok(contents) => {
+                for line in contents.lines() {
+                    let line = line.trim();
+                    if line.is_empty() || line.starts_with('#') { continue; }
+                    exclude_patterns.push(line.to_string());
+                }
+            },
+            Err(e) => log::warn!("Cannot read ignore file FILE: {} ERROR: {:?}", ignore_file, e),
+        }
+    }
+    let include = if include_patterns.is_empty() { None } else { Some(build_glob_set(include_patterns)) };
+    let exclude = build_glob_set(&exclude_patterns);
+    let allowed_extensions = allowed_extensions.map(|extensions| {
+        extensions.split(',')
+            .map(|ext| ext.trim().trim_start_matches('.').to_ascii_lowercase())
+            .filter(|ext| !ext.is_empty())
+            .collect()
+    });
+    PathFilter { include, exclude, allowed_extensions }
+}
+
+// Scan process memory of all processes
+fn scan_processes(compiled_rules: &Rules, scan_config: &ScanConfig, reporter: &FindingsCollector) ->() {
+    // Refresh the process information
+    let mut sys = System::new_all();
+    sys.refresh_all();
+    for (pid, process) in sys.processes() {
+        scan_process(pid.as_u32(), process.name(), compiled_rules, scan_config, reporter);
+    }
+}
+
+// Scan the memory of a single running process. Pulled out of `scan_processes` so the daemon's
+// scan-pid request can target one process without walking the whole process list.
+fn scan_process(pid: u32, process_name: &str, compiled_rules: &Rules, scan_config: &ScanConfig, reporter: &FindingsCollector) -> () {
+    // Debug output : show every process that gets scanned
+    log::debug!("Scanning process PID: {} NAME: {}", pid, process_name);
+    // ------------------------------------------------------------
+    // Matches (all types)
+    let mut proc_matches: Vec<GenMatch> = Vec::new();
+    // ------------------------------------------------------------
+    // YARA scanning
+    let yara_matches =
+        compiled_rules.scan_process(pid, 30);
+    log::debug!("Scan result: {:?}", yara_matches);
+    match &yara_matches {
+        Ok(_) => {},
+        Err(e) => {
+            if scan_config.show_access_errors { log::error!("Error while scanning process memory PROCESS: {} ERROR: {:?}", process_name, e); }
+            else { log::debug!("Error while scanning process memory PROCESS: {} ERROR: {:?}", process_name, e); }
+        }
+    }
+    // TODO: better scan error handling (debug messages)
+    for ymatch in yara_matches.unwrap_or_default().iter() {
+        let match_message: String = format!("YARA match with rule {:?}", ymatch.identifier);
+        //println!("{}", match_message);
+        let strings: Vec<String> = ymatch.strings.iter().map(|s| s.identifier.to_string()).collect();
+        let offsets: Vec<u64> = ymatch.strings.iter()
+            .flat_map(|s| s.matches.iter().map(|m| m.offset as u64)).collect();
+        // TODO: get score from meta data in a safe way
+        proc_matches.push(GenMatch{message: match_message, score: 75, offsets, strings});
+    }
+
+    // Show matches on process
+    if proc_matches.len() > 0 {
+        let total_score: u16 = proc_matches.iter().map(|m| m.score).sum();
+        reporter.report_process_match(pid, process_name, &proc_matches, total_score);
+    }
+}
+
+// Everything a file scan needs that's shared, read-only, across every entry the walker finds -
+// bundled into one struct (instead of each as its own parameter) so scan_path/scan_entry's
+// signatures don't keep growing every time a request adds another piece of shared state.
+struct ScanContext {
+    compiled_rules: Arc<Rules>,
+    scan_config: Arc<ScanConfig>,
+    hash_iocs: Arc<HashMap<HashType, HashMap<String, HashIOC>>>,
+    fuzzy_hash_iocs: Arc<Vec<FuzzyHashIOC>>,
+    mount_info: Arc<MountInfo>,
+    reporter: Arc<FindingsCollector>,
+    needed_hash_types: HashSet<HashType>,
 }
 
 // Scan a given file system path
-fn scan_path (target_folder: String, compiled_rules: &Rules, scan_config: &ScanConfig, hash_iocs: &Vec<HashIOC>) -> () {
-    // Walk the file system
-    for entry in WalkDir::new(target_folder).into_iter().filter_map(|e| e.ok()) {
-        
-        // Skip certain elements
-        // Skip all elements that aren't files
-        if !entry.path().is_file() { 
-            log::trace!("Skipped element that isn't a file ELEMENT: {} TYPE: {:?}", entry.path().display(), entry.path().symlink_metadata());
-            continue;
+fn scan_path (target_folder: String, ctx: Arc<ScanContext>, threads: usize) -> () {
+    // threads == 0 means "let rayon pick a sensible default" (the available parallelism)
+    let mut pool_builder = rayon::ThreadPoolBuilder::new();
+    if threads > 0 { pool_builder = pool_builder.num_threads(threads); }
+    let pool = pool_builder.build().expect("Unable to build the file scan thread pool");
+    // A single walker feeds candidate paths; the pool's workers run hashing/YARA concurrently
+    pool.install(|| {
+        WalkDir::new(target_folder).into_iter().filter_map(|e| e.ok()).par_bridge().for_each(|entry| {
+            scan_entry(&entry, &ctx);
+        });
+    });
+}
+
+// Scan a single file system entry: applied to every candidate path found by `scan_path`'s
+// walker, possibly concurrently across worker threads.
+fn scan_entry (entry: &DirEntry, ctx: &ScanContext) -> () {
+    let compiled_rules: &Rules = &ctx.compiled_rules;
+    let scan_config: &ScanConfig = &ctx.scan_config;
+    let hash_iocs: &HashMap<HashType, HashMap<String, HashIOC>> = &ctx.hash_iocs;
+    let fuzzy_hash_iocs: &Vec<FuzzyHashIOC> = &ctx.fuzzy_hash_iocs;
+    let mount_info: &MountInfo = &ctx.mount_info;
+    let reporter: &FindingsCollector = &ctx.reporter;
+    let needed_hash_types: &HashSet<HashType> = &ctx.needed_hash_types;
+    // Skip certain elements
+    // Skip all elements that aren't files
+    if !entry.path().is_file() {
+        log::trace!("Skipped element that isn't a file ELEMENT: {} TYPE: {:?}", entry.path().display(), entry.path().symlink_metadata());
+        return;
+    };
+    // Skip files on non-local (network/removable) mount points
+    if !mount_info.is_scannable(entry.path()) {
+        log::trace!("Skipping file on non-scannable mount point FILE: {}", entry.path().display());
+        return;
+    };
+    // Skip files excluded by --exclude/--include/--ignore-file/--extensions, before opening them
+    if !scan_config.path_filter.is_allowed(entry.path()) {
+        log::trace!("Skipping file due to include/exclude filters FILE: {}", entry.path().display());
+        return;
+    };
+    // Skip big files
+    let metadata = entry.path().symlink_metadata().unwrap();
+    let realsize = entry.path().size_on_disk_fast(&metadata).unwrap();
+    if realsize > scan_config.max_file_size as u64 {
+        log::trace!("Skipping file due to size FILE: {} SIZE: {} MAX_FILE_SIZE: {}",
+        entry.path().display(), realsize, scan_config.max_file_size);
+        return;
+    }
+    // Skip certain file types
+    let extension = entry.path().extension().unwrap_or_default().to_str().unwrap();
+    let file_format = FileFormat::from_file(entry.path()).unwrap_or_default();
+    let file_format_desc = file_format.to_owned().to_string();
+    let file_format_extension = file_format.name();
+
+    if !FILE_TYPES.contains(&file_format_desc.as_str()) &&  // Include certain file types
+        !REL_EXTS.contains(&extension) &&  // Include extensions that are in the relevant extensions list
+        !scan_config.scan_all_types  // Scan all types if user enforced it via command line flag
+        {
+            log::trace!("Skipping file due to extension or type FILE: {} EXT: {:?} TYPE: {:?}",
+            entry.path().display(), extension, file_format_desc);
+            return;
         };
-        // Skip big files
-        let metadata = entry.path().symlink_metadata().unwrap();
-        let realsize = entry.path().size_on_disk_fast(&metadata).unwrap();
-        if realsize > scan_config.max_file_size as u64 { 
-            log::trace!("Skipping file due to size FILE: {} SIZE: {} MAX_FILE_SIZE: {}", 
-            entry.path().display(), realsize, scan_config.max_file_size);
-            continue; 
+
+    // Debug output : show every file that gets scanned
+    log::debug!("Scanning file {} TYPE: {:?}", entry.path().display(), file_format_desc);
+    
+    // ------------------------------------------------------------
+    // VARS
+    // Matches (all types)
+    let mut sample_matches: Vec<GenMatch> = Vec::new();
+    let mut sample_info: SampleInfo;
+
+    // TIME STAMPS
+    let metadata = fs::metadata(entry.path()).unwrap();
+    let msecs = &metadata.modified().unwrap().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    let asecs = &metadata.accessed().unwrap().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    let csecs = &metadata.created().unwrap().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    let mtime = Utc.timestamp(*msecs as i64, 0);
+    let atime = Utc.timestamp(*asecs as i64, 0);
+    let ctime = Utc.timestamp(*csecs as i64, 0);
+
+    // ------------------------------------------------------------
+    // READ FILE
+    // Read file to data blob
+    let result = fs::File::open(&entry.path());
+    let file_handle = match &result {
+        Ok(data) => data,
+        Err(e) => {
+            if scan_config.show_access_errors { log::error!("Cannot access file FILE: {:?} ERROR: {:?}", entry.path(), e); }
+            else { log::debug!("Cannot access file FILE: {:?} ERROR: {:?}", entry.path(), e); }
+            return; // skip the rest of the analysis
         }
-        // Skip certain file types
-        let extension = entry.path().extension().unwrap_or_default().to_str().unwrap();
-        let file_format = FileFormat::from_file(entry.path()).unwrap_or_default();
-        let file_format_desc = file_format.to_owned().to_string();
-        let file_format_extension = file_format.name();
-
-        if !FILE_TYPES.contains(&file_format_desc.as_str()) &&  // Include certain file types
-            !REL_EXTS.contains(&extension) &&  // Include extensions that are in the relevant extensions list 
-            !scan_config.scan_all_types  // Scan all types if user enforced it via command line flag
-            { 
-                log::trace!("Skipping file due to extension or type FILE: {} EXT: {:?} TYPE: {:?}", 
-                entry.path().display(), extension, file_format_desc);
-                continue; 
-            };
-
-        // Debug output : show every file that gets scanned
-        log::debug!("Scanning file {} TYPE: {:?}", entry.path().display(), file_format_desc);
-        
-        // ------------------------------------------------------------
-        // VARS
-        // Matches (all types)
-        let mut sample_matches = ArrayVec::<GenMatch, 100>::new();
-        let mut sample_info: SampleInfo;
-
-        // TIME STAMPS
-        let metadata = fs::metadata(entry.path()).unwrap();
-        let msecs = &metadata.modified().unwrap().duration_since(UNIX_EPOCH).unwrap().as_secs();
-        let asecs = &metadata.accessed().unwrap().duration_since(UNIX_EPOCH).unwrap().as_secs();
-        let csecs = &metadata.created().unwrap().duration_since(UNIX_EPOCH).unwrap().as_secs();
-        let mtime = Utc.timestamp(*msecs as i64, 0);
-        let atime = Utc.timestamp(*asecs as i64, 0);
-        let ctime = Utc.timestamp(*csecs as i64, 0);
-
-        // ------------------------------------------------------------
-        // READ FILE
-        // Read file to data blob
-        let result = fs::File::open(&entry.path());
-        let file_handle = match &result {
-            Ok(data) => data,
-            Err(e) => { 
-                if scan_config.show_access_errors { log::error!("Cannot access file FILE: {:?} ERROR: {:?}", entry.path(), e); }
-                else { log::debug!("Cannot access file FILE: {:?} ERROR: {:?}", entry.path(), e); }
-                continue; // skip the rest of the analysis 
-            }
-        };
-        let mmap = unsafe { MmapOptions::new().map(&file_handle).unwrap() };
-
-        // ------------------------------------------------------------
-        // IOC Matching
-
-        // Hash Matching
-        // Generate hashes
-        let md5_value = format!("{:x}", md5::compute(&mmap));
-        let sha1_hash_array = Sha1::new()
-            .chain_update(&mmap)
-            .finalize();
-        let sha256_hash_array = Sha256::new()
-            .chain_update(&mmap)
-            .finalize();
-        let sha1_value = hex::encode(&sha1_hash_array);
-        let sha256_value = hex::encode(&sha256_hash_array);
-        //let md5_hash = hex::encode(&md5_hash_array);
-        log::trace!("Hashes of FILE: {:?} SHA256: {} SHA1: {} MD5: {}", entry.path(), sha256_value, sha1_value, md5_value);
-        // Compare hashes with hash IOCs
-        let mut hash_match: bool = false;
-        for hash_ioc in hash_iocs.iter() {
-            if !sample_matches.is_full() {
-                match hash_ioc.hash_type {
-                    HashType::Md5 => { if hash_ioc.hash_value == md5_value { hash_match = true; }}, 
-                    HashType::Sha1 => { if hash_ioc.hash_value == sha1_value { hash_match = true; }}, 
-                    HashType::Sha256 => { if hash_ioc.hash_value == sha256_value { hash_match = true; }}, 
-                    _ => {},
-                }
-            }
-            // Hash Match
-            if hash_match {
-                let match_message: String = format!("HASH match with IOC HASH: {} DESC: {}", hash_ioc.hash_value, hash_ioc.description);
-                sample_matches.insert(
-                    sample_matches.len(), 
-                    // TODO: get meta data in a safe way from Vec structure
-                    GenMatch{message: match_message, score: hash_ioc.score}
-                );
+    };
+    // ------------------------------------------------------------
+    // IOC Matching
+
+    // Hash Matching
+    // Generate only the hashes the IOC set actually needs, streaming or mmap'ing the file
+    // depending on its size so large files never need to fit in address space at once
+    let mut hash_values = match compute_file_hashes(&file_handle, metadata.len(), &needed_hash_types, scan_config) {
+        Ok(hash_values) => hash_values,
+        Err(e) => {
+            if scan_config.show_access_errors { log::error!("Cannot hash file FILE: {:?} ERROR: {:?}", entry.path(), e); }
+            else { log::debug!("Cannot hash file FILE: {:?} ERROR: {:?}", entry.path(), e); }
+            return; // skip the rest of the analysis
+        }
+    };
+    log::trace!("Hashes of FILE: {:?} VALUES: {:?}", entry.path(), hash_values);
+    // Compare hashes with hash IOCs via the per-algorithm index - O(1) per hash instead of
+    // scanning the whole IOC set for every file
+    for (hash_type, hash_value) in hash_values.iter() {
+        if let Some(index) = hash_iocs.get(hash_type) {
+            if let Some(hash_ioc) = index.get(hash_value) {
+                let match_message: String = format!("{} HASH match with IOC HASH: {} DESC: {}", hash_ioc.hash_type.label(), hash_ioc.hash_value, hash_ioc.description);
+                sample_matches.push(GenMatch{message: match_message, score: hash_ioc.score, offsets: Vec::new(), strings: Vec::new()});
             }
         }
-        
-        // ------------------------------------------------------------
-        // SAMPLE INFO 
-        let sample_info = SampleInfo {
-            MD5: md5_value,
-            SHA1: sha1_value,
-            SHA256: sha256_value,
-            atime: atime.to_rfc3339(),
-            mtime: mtime.to_rfc3339(),
-            ctime: ctime.to_rfc3339(),
-        };
+    }
 
-        // ------------------------------------------------------------
-        // YARA scanning
-        // Preparing the external variables
-        let ext_vars = ExtVars{
-            filename: entry.path().file_name().unwrap().to_string_lossy().to_string(),
-            filepath: entry.path().parent().unwrap().to_string_lossy().to_string(),
-            extension: extension.to_string(),
-            filetype: file_format_extension.to_ascii_uppercase(),
-            owner: "".to_string(),  // TODO
-        };
-        log::trace!("Passing external variables to the scan EXT_VARS: {:?}", ext_vars);
-        // Actual scanning and result analysis
-        let yara_matches = 
-            scan_file(&compiled_rules, &file_handle, scan_config, &ext_vars);
-        for ymatch in yara_matches.iter() {
-            if !sample_matches.is_full() {
-                let match_message: String = format!("YARA match with rule {}", ymatch.rulename);
-                sample_matches.insert(
-                    sample_matches.len(), 
-                    // TODO: get meta data in a safe way from Vec structure
-                    GenMatch{message: match_message, score: ymatch.score}
-                );
+    // Import hash: the IOC set only needs it computed when it's actually looking for one.
+    // Driven purely by `compute_imphash`'s own PE parse rather than an extension pre-filter,
+    // so a disguised/renamed PE (e.g. "payload.tmp") isn't silently skipped - it's already
+    // content-typed by `FileFormat::from_file` above, and `PE::parse` no-ops safely on anything
+    // that isn't actually a PE.
+    if let Some(imphash_index) = hash_iocs.get(&HashType::Imphash) {
+        if let Some(imphash) = compute_imphash(file_handle) {
+            if let Some(hash_ioc) = imphash_index.get(&imphash) {
+                let match_message: String = format!("IMPHASH match with IOC HASH: {} DESC: {}", hash_ioc.hash_value, hash_ioc.description);
+                sample_matches.push(GenMatch{message: match_message, score: hash_ioc.score, offsets: Vec::new(), strings: Vec::new()});
             }
+            hash_values.insert(HashType::Imphash, imphash);
         }
-        // Scan Results
-        if sample_matches.len() > 0 {
-            // Calculate a total score
-            let mut total_score: u16 = 0; 
-            for sm in sample_matches.iter() {
-                total_score += sm.score;
+    }
+
+    // Fuzzy (ssdeep) similarity: flags near-matches of known-bad samples above
+    // `scan_config.fuzzy_threshold`, rather than requiring an exact digest match
+    if !fuzzy_hash_iocs.is_empty() {
+        if let Some(file_ssdeep) = compute_ssdeep(file_handle, metadata.len(), scan_config) {
+            for fuzzy_ioc in fuzzy_hash_iocs.iter() {
+                if let Ok(similarity) = ssdeep::compare(file_ssdeep.as_str(), fuzzy_ioc.ssdeep_hash.as_str()) {
+                    if similarity >= scan_config.fuzzy_threshold {
+                        let match_message: String = format!("SSDEEP match ({}% similar) with IOC HASH: {} DESC: {}",
+                            similarity, fuzzy_ioc.ssdeep_hash, fuzzy_ioc.description);
+                        sample_matches.push(GenMatch{message: match_message, score: fuzzy_ioc.score, offsets: Vec::new(), strings: Vec::new()});
+                    }
+                }
             }
-            // Print line
-            // TODO: print all matches in a nested form
-            log::warn!("File match found FILE: {} {:?} SCORE: {} REASONS: {:?}", 
-                entry.path().display(), 
-                sample_info, 
-                total_score, 
-                sample_matches);
         }
     }
+
+    // ------------------------------------------------------------
+    // SAMPLE INFO
+    let sample_info = SampleInfo {
+        hashes: hash_values.iter().map(|(t, v)| (t.label().to_string(), v.clone())).collect(),
+        atime: atime.to_rfc3339(),
+        mtime: mtime.to_rfc3339(),
+        ctime: ctime.to_rfc3339(),
+    };
+
+    // ------------------------------------------------------------
+    // YARA scanning
+    // Preparing the external variables
+    let ext_vars = ExtVars{
+        filename: entry.path().file_name().unwrap().to_string_lossy().to_string(),
+        filepath: entry.path().parent().unwrap().to_string_lossy().to_string(),
+        extension: extension.to_string(),
+        filetype: file_format_extension.to_ascii_uppercase(),
+        owner: "".to_string(),  // TODO
+    };
+    log::trace!("Passing external variables to the scan EXT_VARS: {:?}", ext_vars);
+    // Actual scanning and result analysis
+    let yara_matches = 
+        scan_file(&compiled_rules, &file_handle, scan_config, &ext_vars);
+    for ymatch in yara_matches.iter() {
+        let match_message: String = format!("YARA match with rule {}", ymatch.rulename);
+        sample_matches.push(GenMatch{message: match_message, score: ymatch.score, offsets: ymatch.offsets.clone(), strings: ymatch.strings.clone()});
+    }
+    // Scan Results
+    if sample_matches.len() > 0 {
+        // Calculate a total score
+        let mut total_score: u16 = 0;
+        for sm in sample_matches.iter() {
+            total_score += sm.score;
+        }
+        reporter.report_file_match(entry.path(), &sample_info, &sample_matches, total_score);
+    }
 }
 
 // scan a file
-fn scan_file(rules: &Rules, file_handle: &File, scan_config: &ScanConfig, ext_vars: &ExtVars) -> ArrayVec<YaraMatch, 100> {
+fn scan_file(rules: &Rules, file_handle: &File, scan_config: &ScanConfig, ext_vars: &ExtVars) -> Vec<YaraMatch> {
     // Preparing the external variables
     // Preparing the scanner
     let mut scanner = rules.scanner().unwrap();
@@ -449,21 +974,99 @@ fn scan_file(rules: &Rules, file_handle: &File, scan_config: &ScanConfig, ext_va
         }
     }
     //println!("{:?}", results);
-    let mut yara_matches = ArrayVec::<YaraMatch, 100>::new();
+    let mut yara_matches: Vec<YaraMatch> = Vec::new();
     for _match in results.iter() {
         if _match.len() > 0 {
             log::debug!("MATCH FOUND: {:?} LEN: {}", _match, _match.len());
-            if !yara_matches.is_full() {
-                yara_matches.insert(
-                    yara_matches.len(), 
-                    YaraMatch{rulename: _match[0].identifier.to_string(), score: 60}
-                );
-            }
+            let rule = &_match[0];
+            let strings: Vec<String> = rule.strings.iter().map(|s| s.identifier.to_string()).collect();
+            let offsets: Vec<u64> = rule.strings.iter()
+                .flat_map(|s| s.matches.iter().map(|m| m.offset as u64)).collect();
+            yara_matches.push(YaraMatch{rulename: rule.identifier.to_string(), score: 60, offsets, strings});
         }
     }
     return yara_matches;
 }
 
+// A daemon request, one per connection, read as a single line of JSON. `interprocess` gives us
+// a Unix domain socket on Unix and a named pipe on Windows behind the same API, so the daemon
+// itself doesn't need to special-case the platform.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+enum DaemonRequest {
+    ScanPath { path: String },
+    ScanPid { pid: u32 },
+}
+
+// Run as a resident daemon: rules and IOCs are already loaded by the time this is called, so
+// every request after that skips the initialize_rules/initialize_hash_iocs cost a one-shot run
+// would pay. Serves scan-path/scan-pid requests on `socket_name` until the process is killed.
+fn run_daemon(compiled_rules: Arc<Rules>, scan_config: Arc<ScanConfig>, hash_iocs: Arc<HashMap<HashType, HashMap<String, HashIOC>>>, fuzzy_hash_iocs: Arc<Vec<FuzzyHashIOC>>, hostname: String, socket_name: &str, threads: usize) -> () {
+    let listener = match LocalSocketListener::bind(socket_name) {
+        Ok(listener) => listener,
+        Err(e) => { log::error!("Cannot bind daemon socket NAME: {} ERROR: {:?}", socket_name, e); return; }
+    };
+    log::info!("Daemon listening SOCKET: {}", socket_name);
+    for connection in listener.incoming() {
+        let stream = match connection {
+            Ok(stream) => stream,
+            Err(e) => { log::error!("Cannot accept daemon connection ERROR: {:?}", e); continue; }
+        };
+        handle_daemon_connection(stream, compiled_rules.clone(), scan_config.clone(), hash_iocs.clone(), fuzzy_hash_iocs.clone(), &hostname, threads);
+    }
+}
+
+// Handle a single daemon connection: one NDJSON request line in, one NDJSON response line out
+// (a JSON array of findings), then the connection is done.
+fn handle_daemon_connection(mut stream: LocalSocketStream, compiled_rules: Arc<Rules>, scan_config: Arc<ScanConfig>, hash_iocs: Arc<HashMap<HashType, HashMap<String, HashIOC>>>, fuzzy_hash_iocs: Arc<Vec<FuzzyHashIOC>>, hostname: &str, threads: usize) -> () {
+    let mut request_line = String::new();
+    {
+        let mut reader = io::BufReader::new(&mut stream);
+        if let Err(e) = reader.read_line(&mut request_line) {
+            log::error!("Cannot read daemon request ERROR: {:?}", e);
+            return;
+        }
+    }
+    let request: DaemonRequest = match serde_json::from_str(request_line.trim()) {
+        Ok(request) => request,
+        Err(e) => {
+            log::error!("Cannot parse daemon request REQUEST: {} ERROR: {:?}", request_line.trim(), e);
+            let _ = writeln!(stream, "{}", json!({"error": e.to_string()}));
+            return;
+        }
+    };
+    // Each request gets its own collector, always in JSON mode, so one connection's findings
+    // don't leak into another's and the response is independent of --format/--output
+    let reporter = Arc::new(FindingsCollector::new(OutputFormat::Json, None, hostname.to_string()));
+    match request {
+        DaemonRequest::ScanPath { path } => {
+            log::info!("Daemon scan-path request PATH: {}", path);
+            // Reuses the same pooled walker as a one-shot scan, so a daemon started with
+            // --scan-network-drives / --threads behaves the same as `loki scan` would.
+            let mount_info = Arc::new(classify_mounts(scan_config.scan_network_drives));
+            let needed_hash_types = required_hash_types(&hash_iocs);
+            let ctx = Arc::new(ScanContext {
+                compiled_rules: compiled_rules.clone(),
+                scan_config: scan_config.clone(),
+                hash_iocs: hash_iocs.clone(),
+                fuzzy_hash_iocs: fuzzy_hash_iocs.clone(),
+                mount_info,
+                reporter: reporter.clone(),
+                needed_hash_types,
+            });
+            scan_path(path, ctx, threads);
+        },
+        DaemonRequest::ScanPid { pid } => {
+            log::info!("Daemon scan-pid request PID: {}", pid);
+            scan_process(pid, "unknown", &compiled_rules, &scan_config, &reporter);
+        },
+    }
+    let records: Vec<serde_json::Value> = reporter.findings_snapshot().iter().map(Finding::to_json).collect();
+    if let Err(e) = writeln!(stream, "{}", serde_json::Value::Array(records)) {
+        log::error!("Cannot write daemon response ERROR: {:?}", e);
+    }
+}
+
 // Evaluate platform & environment information
 fn evaluate_env() {
     let mut sys = System::new_all();
@@ -538,7 +1141,111 @@ fn welcome_message() {
     println!("  Version {} (Rust)                                            ", VERSION);
     println!("  Florian Roth 2022                                                     ");
     println!(" ");
-    println!("------------------------------------------------------------------------");                      
+    println!("------------------------------------------------------------------------");
+}
+
+// Lint every YARA signature file (and the fully composed set, mirroring `initialize_rules`)
+// without keeping the compiled rules around: reports unparseable/uncompilable rules, including
+// ones that reference undeclared external variables - YARA itself rejects those as "undefined
+// identifier" compile errors against the variables `compile_yara_rules` declares.
+fn validate_yara_rules() -> Vec<String> {
+    let mut problems: Vec<String> = Vec::new();
+    let yara_sigs_folder = format!("{}/yara", SIGNATURE_SOURCE);
+    let files = match fs::read_dir(&yara_sigs_folder) {
+        Ok(files) => files,
+        Err(e) => { problems.push(format!("Cannot read YARA signature folder {}: {:?}", yara_sigs_folder, e)); return problems; }
+    };
+    let mut all_rules = String::new();
+    for entry in files.filter_map(Result::ok) {
+        if entry.path().extension().map(|e| e == "yar").unwrap_or(false) {
+            match fs::read_to_string(entry.path()) {
+                Ok(rules_string) => {
+                    match compile_yara_rules(&rules_string) {
+                        Ok(_) => all_rules += &rules_string,
+                        Err(e) => problems.push(format!("Rule file {} does not compile: {:?}", entry.path().display(), e)),
+                    }
+                },
+                Err(e) => problems.push(format!("Cannot read rule file {}: {:?}", entry.path().display(), e)),
+            }
+        }
+    }
+    // Only worth testing the merged set if every individual file already compiled on its own
+    if problems.is_empty() {
+        if let Err(e) = compile_yara_rules(&all_rules) {
+            problems.push(format!("Composed rule set does not compile: {:?}", e));
+        }
+    }
+    problems
+}
+
+// Lint the hash IOC file without building the runtime index: flags unparseable/invalid hash
+// values and duplicate entries, so a bad IOC file update fails `validate` instead of silently
+// shadowing an earlier entry mid-scan.
+fn validate_hash_iocs() -> Vec<String> {
+    let mut problems: Vec<String> = Vec::new();
+    let hash_ioc_file = format!("{}/iocs/hash-iocs.txt", SIGNATURE_SOURCE);
+    let hash_iocs_string = match fs::read_to_string(&hash_ioc_file) {
+        Ok(s) => s,
+        Err(e) => { problems.push(format!("Cannot read hash IOC file {}: {:?}", hash_ioc_file, e)); return problems; }
+    };
+    let mut reader = ReaderBuilder::new()
+        .delimiter(b';')
+        .flexible(true)
+        .from_reader(hash_iocs_string.as_bytes());
+    let mut seen: HashSet<(HashType, String)> = HashSet::new();
+    for result in reader.records() {
+        let record = match result {
+            Ok(r) => r,
+            Err(e) => { problems.push(format!("Line {}: cannot parse CSV record: {:?}", e.position().map_or(0, |p| p.line()), e)); continue; }
+        };
+        // File line of this record (the CSV reader consumes the first line as a header row)
+        let line_number = record.position().map_or(0, |p| p.line());
+        if record.len() <= 1 || record[0].starts_with("#") { continue; }
+        let (explicit_type, _description) = if record.len() > 2 { (Some(&record[1]), &record[2]) } else { (None, &record[1]) };
+        // ssdeep hashes aren't plain hex, so they're validated separately from the exact-match types
+        if explicit_type.map(|t| t.eq_ignore_ascii_case("ssdeep")).unwrap_or(false) {
+            if record[0].matches(':').count() < 2 {
+                problems.push(format!("Line {}: malformed ssdeep hash '{}'", line_number, &record[0]));
+            }
+            continue;
+        }
+        let hash_type = get_hash_type(&record[0], explicit_type);
+        if hash_type == HashType::Unknown || !record[0].chars().all(|c| c.is_ascii_hexdigit()) {
+            problems.push(format!("Line {}: invalid hash value '{}' (resolved type {:?})", line_number, &record[0], hash_type));
+            continue;
+        }
+        let hash_value = record[0].to_ascii_lowercase();
+        if !seen.insert((hash_type, hash_value.clone())) {
+            problems.push(format!("Line {}: duplicate {:?} hash '{}'", line_number, hash_type, hash_value));
+        }
+    }
+    problems
+}
+
+// `validate` subcommand: lint the rule/IOC set and return a process exit code, so CI pipelines
+// can verify a signature update before it reaches a scanning host instead of discovering
+// breakage mid-scan.
+fn run_validate() -> i32 {
+    println!("Validating YARA rules and hash IOCs ...");
+    let mut problems = validate_yara_rules();
+    problems.extend(validate_hash_iocs());
+    if problems.is_empty() {
+        println!("OK: no issues found");
+        0
+    } else {
+        for problem in &problems {
+            eprintln!("ERROR: {}", problem);
+        }
+        eprintln!("{} issue(s) found", problems.len());
+        1
+    }
+}
+
+// `list-modules` subcommand: print the available scan modules and exit
+fn run_list_modules() {
+    for module in MODULES {
+        println!("{}", module);
+    }
 }
 
 fn main() {
@@ -546,23 +1253,64 @@ fn main() {
     // Show welcome message
     welcome_message();
 
+    // Subcommand dispatch: `scan` is the default (the original single-entry-point behavior,
+    // still reached with no subcommand or any flags/positional folder argument), `validate`
+    // lints rules/IOCs for CI, and `list-modules` prints the available scan modules. Checked
+    // ahead of the main flag parser so validate/list-modules don't have to share its flag set.
+    // `scan` itself is stripped from the argument list below so it never reaches the flag
+    // parser as a stray positional token ahead of the real folder argument.
+    let mut cli_args: Vec<String> = env::args().skip(1).collect();
+    match cli_args.first().map(String::as_str) {
+        Some("validate") => std::process::exit(run_validate()),
+        Some("list-modules") => { run_list_modules(); return; },
+        Some("scan") => { cli_args.remove(0); },
+        _ => {},
+    }
+
     // Parsing command line flags
     let (args, _rest) = opts! {
         synopsis "LOKI YARA and IOC Scanner";
-        opt max_file_size:usize=10_000_000, desc:"Maximum file size to scan";
+        opt max_file_size:usize=1_073_741_824, desc:"Maximum file size to scan";
         opt show_access_errors:bool, desc:"Show all file and process access errors";
         opt scan_all_files:bool, desc:"Scan all files regardless of their file type / extension";
+        opt scan_network_drives:bool, desc:"Also scan network/remote and removable mounts (skipped by default)";
+        opt exclude:Option<String>, desc:"Comma-separated glob patterns; matching paths are skipped during the file scan";
+        opt include:Option<String>, desc:"Comma-separated glob patterns; when given, only matching paths are scanned";
+        opt ignore_file:Option<String>, desc:"Path to a .gitignore-style file of additional exclude glob patterns";
+        opt extensions:Option<String>, desc:"Comma-separated list of file extensions to restrict the scan to";
+        opt streaming_threshold:usize=104_857_600, desc:"File size above which hashing streams the file instead of mmap'ing it";
+        opt streaming_buffer_size:usize=1_048_576, desc:"Buffer size used when streaming large files for hashing";
+        opt fuzzy_threshold:u8=70, desc:"Minimum ssdeep similarity score (0-100) for a fuzzy hash IOC to be reported";
         opt debug:bool, desc:"Show debugging information";
         opt trace:bool, desc:"Show very verbose trace output";
         opt noprocs:bool, desc:"Don't scan processes";
         opt nofs:bool, desc:"Don't scan the file system";
-        opt folder:Option<String>, desc:"Folder to scan"; // an optional (positional) parameter
-    }.parse_or_exit();
+        opt threads:usize=0, desc:"Worker threads for file scanning (0 = automatic)";
+        opt output:Option<String>, desc:"Write structured findings to this file (see --format)";
+        opt format:Option<String>, desc:"Findings format written to --output: text (default), json or ndjson";
+        opt json_out:Option<String>, desc:"Deprecated alias for --output with --format ndjson";
+        opt daemon:bool, desc:"Stay resident and serve scan-path/scan-pid requests over a local socket instead of scanning once";
+        opt socket_name:Option<String>, desc:"Local socket name to listen on in --daemon mode (default: loki-daemon)";
+        param folder:Option<String>, desc:"Folder to scan"; // an optional positional parameter
+    }.parse_args(cli_args.iter().map(String::as_str)).unwrap_or_else(|err| match err {
+        rustop::Error::Help(msg) => { eprintln!("{}", msg); std::process::exit(1); },
+        err => rustop::error_and_exit(&err),
+    });
     // Create a config
+    let exclude_patterns: Vec<String> = args.exclude.as_deref().unwrap_or("")
+        .split(',').map(str::trim).filter(|s| !s.is_empty()).map(str::to_string).collect();
+    let include_patterns: Vec<String> = args.include.as_deref().unwrap_or("")
+        .split(',').map(str::trim).filter(|s| !s.is_empty()).map(str::to_string).collect();
+    let path_filter = build_path_filter(&include_patterns, &exclude_patterns, args.ignore_file.as_deref(), args.extensions.as_deref());
     let scan_config = ScanConfig {
         max_file_size: args.max_file_size,
         show_access_errors: args.show_access_errors,
         scan_all_types: args.scan_all_files,
+        streaming_threshold: args.streaming_threshold,
+        streaming_buffer_size: args.streaming_buffer_size,
+        path_filter,
+        fuzzy_threshold: args.fuzzy_threshold,
+        scan_network_drives: args.scan_network_drives,
     };
 
     // Logger
@@ -571,7 +1319,8 @@ fn main() {
     if args.trace { log_level = "trace".to_string(); std_out = Duplicate::Trace; }  // set to trace level
     let mut sys = System::new_all();
     sys.refresh_all();
-    let log_file_name = format!("loki_{}", sys.host_name().unwrap());
+    let hostname = sys.host_name().unwrap();
+    let log_file_name = format!("loki_{}", hostname);
     Logger::try_with_str(log_level).unwrap()
         .log_to_file(
             FileSpec::default()
@@ -607,27 +1356,65 @@ fn main() {
         target_folder = args_target_folder;
     }
     
-    // Initialize IOCs 
+    // Initialize IOCs
     // TODO: not ready yet
     log::info!("Initialize hash IOCs ...");
-    let hash_iocs = initialize_hash_iocs();
+    let (hash_iocs, fuzzy_hash_iocs) = initialize_hash_iocs();
+    let hash_iocs = Arc::new(hash_iocs);
+    let fuzzy_hash_iocs = Arc::new(fuzzy_hash_iocs);
 
     // Initialize the rules
     log::info!("Initializing YARA rules ...");
-    let compiled_rules = initialize_rules();
+    let compiled_rules = Arc::new(initialize_rules());
+    let scan_config = Arc::new(scan_config);
+
+    // Daemon mode: rules and IOCs above are loaded once, then this serves requests until the
+    // process is killed (it returns only if the socket can't be bound) - every scan-path/scan-pid
+    // request after that skips the initialization cost a one-shot run pays.
+    if args.daemon {
+        let socket_name = args.socket_name.unwrap_or_else(|| "loki-daemon".to_string());
+        run_daemon(compiled_rules, scan_config, hash_iocs, fuzzy_hash_iocs, hostname, &socket_name, args.threads);
+        return;
+    }
+
+    // Findings collector: always logs, and also writes --output if one was given and the format
+    // is json or ndjson (the default text format writes no file). Shared
+    // across scan worker threads, hence the Arc. --json-out is a deprecated alias for the
+    // single-purpose NDJSON reporter --output/--format replaced; still honored so it doesn't
+    // break existing callers, but --output/--format win if both are given.
+    let (output_path, output_format) = match (args.output, args.json_out) {
+        (Some(output), _) => (Some(output), args.format.as_deref().map(OutputFormat::parse).unwrap_or(OutputFormat::Text)),
+        (None, Some(json_out)) => (Some(json_out), args.format.as_deref().map(OutputFormat::parse).unwrap_or(OutputFormat::Ndjson)),
+        (None, None) => (None, args.format.as_deref().map(OutputFormat::parse).unwrap_or(OutputFormat::Text)),
+    };
+    let reporter = Arc::new(FindingsCollector::new(output_format, output_path, hostname));
 
     // Process scan
     if active_modules.contains(&"ProcessCheck".to_owned()) {
         log::info!("Scanning running processes ... ");
-        scan_processes(&compiled_rules, &scan_config);
+        scan_processes(&compiled_rules, &scan_config, &reporter);
     }
 
     // File system scan
     if active_modules.contains(&"FileScan".to_owned()) {
         log::info!("Scanning local file system ... ");
-        scan_path(target_folder, &compiled_rules, &scan_config, &hash_iocs);
+        let mount_info = Arc::new(classify_mounts(scan_config.scan_network_drives));
+        let needed_hash_types = required_hash_types(&hash_iocs);
+        let ctx = Arc::new(ScanContext {
+            compiled_rules: compiled_rules.clone(),
+            scan_config: scan_config.clone(),
+            hash_iocs: hash_iocs.clone(),
+            fuzzy_hash_iocs: fuzzy_hash_iocs.clone(),
+            mount_info,
+            reporter: reporter.clone(),
+            needed_hash_types,
+        });
+        scan_path(target_folder, ctx, args.threads);
     }
 
+    // Write the collected findings, if --output was configured with a json or ndjson format
+    reporter.finalize();
+
     // Finished scan
     log::info!("LOKI scan finished");
 }
\ No newline at end of file